@@ -0,0 +1,197 @@
+//! A timeout-aware analogue of a bidirectional byte-shuttling copy, the sort
+//! of pump loop a proxy or tunnel needs to run between two connections.
+
+use futures::{Async, Future, Poll};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+use tokio_core::reactor::Handle;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use super::TimeoutStream;
+
+macro_rules! try_nb {
+    ($e:expr) => {
+        match $e {
+            Ok(t) => t,
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::WouldBlock => {
+                return Ok(Async::NotReady)
+            }
+            Err(e) => return Err(e),
+        }
+    };
+}
+
+/// Creates a future which shuttles bytes in both directions between `a` and
+/// `b` until both sides have reached EOF, applying `read_timeout` to every
+/// read and `write_timeout` to every write on both streams. Both streams are
+/// shut down once both directions are done.
+///
+/// If either direction stalls past its timeout, the returned future resolves
+/// to an `ErrorKind::TimedOut` error and both streams are shut down. On
+/// success it resolves to the number of bytes copied `(a to b, b to a)`.
+pub fn copy_bidirectional_with_timeout<A, B>(
+    a: A,
+    b: B,
+    handle: &Handle,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+) -> io::Result<CopyBidirectionalWithTimeout<A, B>>
+where
+    A: AsyncRead + AsyncWrite,
+    B: AsyncRead + AsyncWrite,
+{
+    let mut a = TimeoutStream::new(a, handle)?;
+    a.set_read_timeout(read_timeout);
+    a.set_write_timeout(write_timeout);
+
+    let mut b = TimeoutStream::new(b, handle)?;
+    b.set_read_timeout(read_timeout);
+    b.set_write_timeout(write_timeout);
+
+    Ok(CopyBidirectionalWithTimeout {
+        a,
+        b,
+        a_to_b: CopyBuffer::new(),
+        b_to_a: CopyBuffer::new(),
+    })
+}
+
+/// A future produced by `copy_bidirectional_with_timeout`.
+pub struct CopyBidirectionalWithTimeout<A, B> {
+    a: TimeoutStream<A>,
+    b: TimeoutStream<B>,
+    a_to_b: CopyBuffer,
+    b_to_a: CopyBuffer,
+}
+
+impl<A, B> Future for CopyBidirectionalWithTimeout<A, B>
+where
+    A: AsyncRead + AsyncWrite,
+    B: AsyncRead + AsyncWrite,
+{
+    type Item = (u64, u64);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<(u64, u64), io::Error> {
+        let a_to_b = self.a_to_b.poll_copy(&mut self.a, &mut self.b);
+        let b_to_a = self.b_to_a.poll_copy(&mut self.b, &mut self.a);
+
+        match (a_to_b, b_to_a) {
+            (Err(e), _) | (_, Err(e)) => {
+                let _ = self.a.shutdown();
+                let _ = self.b.shutdown();
+                Err(e)
+            }
+            (Ok(Async::Ready(a_to_b)), Ok(Async::Ready(b_to_a))) => {
+                if self.a.shutdown()?.is_not_ready() {
+                    return Ok(Async::NotReady);
+                }
+                if self.b.shutdown()?.is_not_ready() {
+                    return Ok(Async::NotReady);
+                }
+                Ok(Async::Ready((a_to_b, b_to_a)))
+            }
+            _ => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// Pumps bytes from one side of a `CopyBidirectionalWithTimeout` to the
+/// other through a fixed internal buffer, in the style of `tokio_io::io::copy`.
+struct CopyBuffer {
+    read_done: bool,
+    pos: usize,
+    cap: usize,
+    amt: u64,
+    buf: Box<[u8]>,
+}
+
+impl CopyBuffer {
+    fn new() -> CopyBuffer {
+        CopyBuffer {
+            read_done: false,
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            buf: Box::new([0; 2048]),
+        }
+    }
+
+    fn poll_copy<R: Read, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> Poll<u64, io::Error> {
+        loop {
+            if self.pos == self.cap && !self.read_done {
+                let n = try_nb!(reader.read(&mut self.buf));
+                if n == 0 {
+                    self.read_done = true;
+                } else {
+                    self.pos = 0;
+                    self.cap = n;
+                }
+            }
+
+            while self.pos < self.cap {
+                let n = try_nb!(writer.write(&self.buf[self.pos..self.cap]));
+                self.pos += n;
+                self.amt += n as u64;
+            }
+
+            if self.pos == self.cap && self.read_done {
+                try_nb!(writer.flush());
+                return Ok(Async::Ready(self.amt));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{Future, Stream};
+    use std::io::{Read, Write};
+    use std::net::TcpStream as StdTcpStream;
+    use std::thread;
+    use tokio_core::net::TcpListener;
+    use tokio_core::reactor::Core;
+
+    use super::copy_bidirectional_with_timeout;
+
+    #[test]
+    fn copies_both_directions() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let listener_a = TcpListener::bind(&"127.0.0.1:0".parse().unwrap(), &handle).unwrap();
+        let addr_a = listener_a.local_addr().unwrap();
+        let listener_b = TcpListener::bind(&"127.0.0.1:0".parse().unwrap(), &handle).unwrap();
+        let addr_b = listener_b.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut client = StdTcpStream::connect(addr_a).unwrap();
+            client.write_all(b"ping").unwrap();
+            let mut buf = [0; 4];
+            client.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"pong");
+        });
+        thread::spawn(move || {
+            let mut client = StdTcpStream::connect(addr_b).unwrap();
+            let mut buf = [0; 4];
+            client.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"ping");
+            client.write_all(b"pong").unwrap();
+        });
+
+        let f = listener_a
+            .incoming()
+            .into_future()
+            .map_err(|(e, _)| e)
+            .join(listener_b.incoming().into_future().map_err(|(e, _)| e))
+            .and_then(move |((a, _), (b, _))| {
+                let (a, _) = a.unwrap();
+                let (b, _) = b.unwrap();
+                copy_bidirectional_with_timeout(a, b, &handle, None, None).unwrap()
+            });
+
+        let (a_to_b, b_to_a) = core.run(f).unwrap();
+        assert_eq!(a_to_b, 4);
+        assert_eq!(b_to_a, 4);
+    }
+}
@@ -13,25 +13,59 @@ extern crate tokio_io;
 extern crate tokio_service;
 
 use bytes::{Buf, BufMut};
+use futures::sync::BiLock;
 use futures::{Async, Future, Poll};
 use std::time::{Duration, Instant};
 use std::io::{self, Read, Write};
+use std::rc::Rc;
 use tokio_core::reactor::{Handle, Timeout};
 use tokio_io::{AsyncRead, AsyncWrite};
 
+mod copy;
+pub use copy::{copy_bidirectional_with_timeout, CopyBidirectionalWithTimeout};
+
 struct TimeoutState {
+    handle: Rc<Handle>,
     timeout: Option<Duration>,
-    cur: Timeout,
+    cur: Option<Timeout>,
     active: bool,
+    deadline: Option<Duration>,
+    cur_deadline: Option<Timeout>,
+    deadline_active: bool,
+    min_rate: Option<(usize, Duration)>,
+    transferred: usize,
+    window_start: Instant,
+    cur_window: Option<Timeout>,
+    window_active: bool,
 }
 
 impl TimeoutState {
-    fn new(handle: &Handle) -> io::Result<TimeoutState> {
-        Ok(TimeoutState {
+    /// Creates a new, unconfigured `TimeoutState` bound to `handle`.
+    ///
+    /// `handle` is an `Rc` so that a `TimeoutReader` and `TimeoutWriter`
+    /// sharing one underlying stream (as in `TimeoutStream`) can share one
+    /// `Handle` between their two `TimeoutState`s instead of each holding an
+    /// independent clone of it.
+    ///
+    /// No `Timeout` is registered with the reactor until a timeout, deadline
+    /// or minimum rate is actually configured and hit on its first `check`;
+    /// a `TimeoutState` that's never given one costs nothing beyond the
+    /// shared handle.
+    fn new(handle: Rc<Handle>) -> TimeoutState {
+        TimeoutState {
+            handle,
             timeout: None,
-            cur: Timeout::new(Duration::from_secs(0), handle)?,
+            cur: None,
             active: false,
-        })
+            deadline: None,
+            cur_deadline: None,
+            deadline_active: false,
+            min_rate: None,
+            transferred: 0,
+            window_start: Instant::now(),
+            cur_window: None,
+            window_active: false,
+        }
     }
 
     #[inline]
@@ -45,31 +79,179 @@ impl TimeoutState {
         self.reset();
     }
 
+    #[inline]
+    fn deadline(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    #[inline]
+    fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.deadline = deadline;
+        self.reset();
+    }
+
+    #[inline]
+    fn set_min_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.min_rate = Some((min_bytes, window));
+        self.transferred = 0;
+        self.window_active = false;
+    }
+
+    /// Records that a read or write made progress, restarting the idle timer
+    /// and crediting the bytes transferred toward the current minimum-rate
+    /// window.
+    #[inline]
+    fn success(&mut self, n: usize) {
+        self.reset_idle();
+        self.transferred = self.transferred.saturating_add(n);
+    }
+
+    /// Resets both the idle timer and the deadline timer.
+    ///
+    /// This is called whenever the timeout or deadline configuration
+    /// changes; an in-progress deadline is only ever cleared here, never by
+    /// a successful read or write.
     #[inline]
     fn reset(&mut self) {
+        self.reset_idle();
+        if self.deadline_active {
+            self.deadline_active = false;
+            if let Some(cur_deadline) = self.cur_deadline.as_mut() {
+                cur_deadline.reset(Instant::now());
+            }
+        }
+    }
+
+    /// Resets just the idle timer, leaving any in-progress deadline alone.
+    ///
+    /// This is what a successful read or write triggers: it proves the
+    /// connection is making progress, which should restart the idle
+    /// countdown but must not let a slow peer dribble bytes forever to dodge
+    /// the absolute deadline.
+    #[inline]
+    fn reset_idle(&mut self) {
         if self.active {
             self.active = false;
-            self.cur.reset(Instant::now());
+            if let Some(cur) = self.cur.as_mut() {
+                cur.reset(Instant::now());
+            }
         }
     }
 
+    /// Arms `timer` for `at`, lazily registering it with the reactor the
+    /// first time it's actually needed.
     #[inline]
-    fn check(&mut self) -> io::Result<()> {
-        let timeout = match self.timeout {
-            Some(timeout) => timeout,
-            None => return Ok(()),
-        };
+    fn arm(timer: &mut Option<Timeout>, at: Instant, handle: &Handle) -> io::Result<()> {
+        match *timer {
+            Some(ref mut timer) => timer.reset(at),
+            None => *timer = Some(Timeout::new_at(at, handle)?),
+        }
+        Ok(())
+    }
 
-        if !self.active {
-            self.cur.reset(Instant::now() + timeout);
-            self.active = true;
+    /// Checks only the idle timer.
+    ///
+    /// Split out from `check` so that callers layering something on top of
+    /// the idle timeout specifically (e.g. `TimeoutWriter`'s idle handler)
+    /// can react to it without also swallowing a deadline or min-rate
+    /// timeout that happens to be checked in the same pass.
+    #[inline]
+    fn check_idle(&mut self) -> io::Result<()> {
+        if let Some(timeout) = self.timeout {
+            if !self.active {
+                TimeoutState::arm(&mut self.cur, Instant::now() + timeout, &self.handle)?;
+                self.active = true;
+            }
+
+            if self.cur.as_mut().unwrap().poll()?.is_ready() {
+                return Err(io::Error::from(io::ErrorKind::TimedOut));
+            }
         }
 
-        if self.cur.poll()?.is_ready() {
-            Err(io::Error::from(io::ErrorKind::TimedOut))
-        } else {
-            Ok(())
+        Ok(())
+    }
+
+    /// Checks only the absolute deadline.
+    #[inline]
+    fn check_deadline(&mut self) -> io::Result<()> {
+        if let Some(deadline) = self.deadline {
+            if !self.deadline_active {
+                TimeoutState::arm(&mut self.cur_deadline, Instant::now() + deadline, &self.handle)?;
+                self.deadline_active = true;
+            }
+
+            if self.cur_deadline.as_mut().unwrap().poll()?.is_ready() {
+                return Err(io::Error::from(io::ErrorKind::TimedOut));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks only the minimum-rate window.
+    #[inline]
+    fn check_min_rate(&mut self) -> io::Result<()> {
+        if let Some((min_bytes, window)) = self.min_rate {
+            if !self.window_active {
+                self.window_start = Instant::now();
+                TimeoutState::arm(&mut self.cur_window, self.window_start + window, &self.handle)?;
+                self.window_active = true;
+            }
+
+            // Rolling a window just rearms `cur_window`, which under futures
+            // 0.1 only registers the current task with the reactor once
+            // it's polled again; loop so a healthy window immediately
+            // followed by a silent one still wakes us at the next boundary
+            // instead of going unregistered until some other timer happens
+            // to poll us first.
+            while self.cur_window.as_mut().unwrap().poll()?.is_ready() {
+                if self.transferred >= min_bytes {
+                    self.window_start += window;
+                    TimeoutState::arm(&mut self.cur_window, self.window_start + window, &self.handle)?;
+                    self.transferred = 0;
+                } else {
+                    return Err(io::Error::from(io::ErrorKind::TimedOut));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn check(&mut self) -> io::Result<()> {
+        self.check_idle()?;
+        self.check_deadline()?;
+        self.check_min_rate()
+    }
+
+    /// Checks the idle timer, giving `on_idle` (if any) a chance to run in
+    /// place of a hard `TimedOut` failure, then checks the deadline and
+    /// minimum rate unconditionally.
+    ///
+    /// Shared by `TimeoutWriter` and `WriteHalf`, the two places that pair a
+    /// `TimeoutState` with an idle handler. Only the idle timer is routed
+    /// through the handler: an expired deadline or minimum rate must still
+    /// fail the write even when an idle handler is installed, so they're
+    /// checked separately rather than through `check`, which would fold all
+    /// three together and let the handler rearm ones it was never meant to
+    /// touch.
+    fn check_with_idle_handler(
+        &mut self,
+        on_idle: &mut Option<Box<FnMut() -> io::Result<()>>>,
+    ) -> io::Result<()> {
+        match self.check_idle() {
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => match *on_idle {
+                Some(ref mut handler) => {
+                    handler()?;
+                    self.reset_idle();
+                }
+                None => return Err(io::Error::from(io::ErrorKind::TimedOut)),
+            },
+            r => r?,
         }
+        self.check_deadline()?;
+        self.check_min_rate()
     }
 }
 
@@ -83,14 +265,24 @@ impl<R> TimeoutReader<R>
 where
     R: AsyncRead,
 {
-    /// Returns a new `TimeoutReader` wrapping the specified reader.
+    /// Returns a new `TimeoutReader` wrapping the specified reader, using
+    /// `handle` to register any timers it ends up needing.
     ///
     /// There is initially no timeout.
     pub fn new(reader: R, handle: &Handle) -> io::Result<TimeoutReader<R>> {
-        Ok(TimeoutReader {
+        Ok(TimeoutReader::with_shared_handle(reader, Rc::new(handle.clone())))
+    }
+
+    /// Like `new`, but takes a handle already shared via `Rc` rather than
+    /// cloning a fresh one.
+    ///
+    /// This is what lets `TimeoutStream` give its `TimeoutReader` and
+    /// `TimeoutWriter` halves the same underlying `Handle`.
+    fn with_shared_handle(reader: R, handle: Rc<Handle>) -> TimeoutReader<R> {
+        TimeoutReader {
             reader,
-            state: TimeoutState::new(handle)?,
-        })
+            state: TimeoutState::new(handle),
+        }
     }
 
     /// Returns the current read timeout.
@@ -105,6 +297,32 @@ where
         self.state.set_timeout(timeout);
     }
 
+    /// Returns the current read deadline.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.state.deadline()
+    }
+
+    /// Sets an absolute deadline for a read to complete within.
+    ///
+    /// Unlike the idle timeout, the deadline is not reset by progress: once
+    /// armed on the first read after this call that would block, it keeps counting
+    /// down no matter how many individual reads succeed in the meantime.
+    /// This will reset any pending deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.state.set_deadline(deadline);
+    }
+
+    /// Requires at least `min_bytes` to be read in each `window`, resetting
+    /// the byte counter every time the window elapses.
+    ///
+    /// This catches peers who stay technically alive by trickling a byte
+    /// through just often enough to dodge the idle timeout: a connection
+    /// transferring less than `min_bytes` per `window` is treated as timed
+    /// out even though individual reads keep succeeding.
+    pub fn set_min_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.state.set_min_rate(min_bytes, window);
+    }
+
     /// Returns a shared reference to the inner reader.
     pub fn get_ref(&self) -> &R {
         &self.reader
@@ -128,8 +346,9 @@ where
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let r = self.reader.read(buf);
         match r {
+            Ok(n) => self.state.success(n),
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.state.check()?,
-            _ => self.state.reset(),
+            Err(_) => self.state.reset_idle(),
         }
         r
     }
@@ -146,8 +365,9 @@ where
     fn read_buf<B: BufMut>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
         let r = self.reader.read_buf(buf);
         match r {
+            Ok(Async::Ready(n)) => self.state.success(n),
             Ok(Async::NotReady) => self.state.check()?,
-            _ => self.state.reset(),
+            Err(_) => self.state.reset_idle(),
         }
         r
     }
@@ -183,20 +403,32 @@ where
 pub struct TimeoutWriter<W> {
     writer: W,
     state: TimeoutState,
+    on_idle: Option<Box<FnMut() -> io::Result<()>>>,
 }
 
 impl<W> TimeoutWriter<W>
 where
     W: AsyncWrite,
 {
-    /// Returns a new `TimeoutReader` wrapping the specified reader.
+    /// Returns a new `TimeoutWriter` wrapping the specified writer, using
+    /// `handle` to register any timers it ends up needing.
     ///
     /// There is initially no timeout.
     pub fn new(writer: W, handle: &Handle) -> io::Result<TimeoutWriter<W>> {
-        Ok(TimeoutWriter {
+        Ok(TimeoutWriter::with_shared_handle(writer, Rc::new(handle.clone())))
+    }
+
+    /// Like `new`, but takes a handle already shared via `Rc` rather than
+    /// cloning a fresh one.
+    ///
+    /// This is what lets `TimeoutStream` give its `TimeoutReader` and
+    /// `TimeoutWriter` halves the same underlying `Handle`.
+    fn with_shared_handle(writer: W, handle: Rc<Handle>) -> TimeoutWriter<W> {
+        TimeoutWriter {
             writer,
-            state: TimeoutState::new(handle)?,
-        })
+            state: TimeoutState::new(handle),
+            on_idle: None,
+        }
     }
 
     /// Returns the current write timeout.
@@ -211,6 +443,45 @@ where
         self.state.set_timeout(timeout);
     }
 
+    /// Returns the current write deadline.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.state.deadline()
+    }
+
+    /// Sets an absolute deadline for a write to complete within.
+    ///
+    /// Unlike the idle timeout, the deadline is not reset by progress: once
+    /// armed on the first write after this call that would block, it keeps counting
+    /// down no matter how many individual writes succeed in the meantime.
+    /// This will reset any pending deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.state.set_deadline(deadline);
+    }
+
+    /// Requires at least `min_bytes` to be written in each `window`,
+    /// resetting the byte counter every time the window elapses.
+    ///
+    /// This catches peers who stay technically alive by trickling a byte
+    /// through just often enough to dodge the idle timeout: a connection
+    /// transferring less than `min_bytes` per `window` is treated as timed
+    /// out even though individual writes keep succeeding.
+    pub fn set_min_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.state.set_min_rate(min_bytes, window);
+    }
+
+    /// Sets a handler to run when the idle timer expires, in place of
+    /// failing the write with `TimedOut`.
+    ///
+    /// If the handler returns `Ok(())`, the idle timer is rearmed and the
+    /// write is retried as if it had simply blocked; a typical handler
+    /// queues a ping or other keepalive frame on a channel shared with the
+    /// writer. If the handler returns an error, that error is surfaced in
+    /// its place. This turns a hard idle-timeout failure into a hook for
+    /// building a keepalive on top of `TimeoutWriter`.
+    pub fn set_idle_handler(&mut self, handler: Option<Box<FnMut() -> io::Result<()>>>) {
+        self.on_idle = handler;
+    }
+
     /// Returns a shared reference to the inner writer.
     pub fn get_ref(&self) -> &W {
         &self.writer
@@ -225,6 +496,13 @@ where
     pub fn into_inner(self) -> W {
         self.writer
     }
+
+    /// Checks the idle timer, giving the idle handler (if any) a chance to
+    /// run in place of a hard `TimedOut` failure, then checks the deadline
+    /// and minimum rate unconditionally.
+    fn check(&mut self) -> io::Result<()> {
+        self.state.check_with_idle_handler(&mut self.on_idle)
+    }
 }
 
 impl<W> Write for TimeoutWriter<W>
@@ -234,8 +512,9 @@ where
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let r = self.writer.write(buf);
         match r {
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.state.check()?,
-            _ => self.state.reset(),
+            Ok(n) => self.state.success(n),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.check()?,
+            Err(_) => self.state.reset_idle(),
         }
         r
     }
@@ -243,8 +522,8 @@ where
     fn flush(&mut self) -> io::Result<()> {
         let r = self.writer.flush();
         match r {
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.state.check()?,
-            _ => self.state.reset(),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.check()?,
+            _ => self.state.reset_idle(),
         }
         r
     }
@@ -257,8 +536,8 @@ where
     fn shutdown(&mut self) -> Poll<(), io::Error> {
         let r = self.writer.shutdown();
         match r {
-            Ok(Async::NotReady) => self.state.check()?,
-            _ => self.state.reset(),
+            Ok(Async::NotReady) => self.check()?,
+            _ => self.state.reset_idle(),
         }
         r
     }
@@ -266,8 +545,9 @@ where
     fn write_buf<B: Buf>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
         let r = self.writer.write_buf(buf);
         match r {
-            Ok(Async::NotReady) => self.state.check()?,
-            _ => self.state.reset(),
+            Ok(Async::Ready(n)) => self.state.success(n),
+            Ok(Async::NotReady) => self.check()?,
+            Err(_) => self.state.reset_idle(),
         }
         r
     }
@@ -296,19 +576,32 @@ where
 }
 
 /// A stream which applies read and write timeouts to an inner stream.
-// TODO this stores two copies of the Handle which is maybe not great?
+///
+/// The read and write sides are configured independently, so each keeps its
+/// own `TimeoutState`, but the two states share a single `Rc<Handle>` rather
+/// than each holding an independent clone, and neither one registers a
+/// `Timeout` with the reactor until it is actually armed: constructing a
+/// `TimeoutStream` that never sets a timeout, deadline or minimum rate costs
+/// nothing beyond that one shared handle.
 pub struct TimeoutStream<S>(TimeoutReader<TimeoutWriter<S>>);
 
 impl<S> TimeoutStream<S>
 where
     S: AsyncRead + AsyncWrite,
 {
-    /// Returns a new `TimeoutStream` wrapping the specified stream.
+    /// Returns a new `TimeoutStream` wrapping the specified stream, sharing
+    /// one `Handle` between its read and write halves.
     ///
     /// There is initially no read or write timeout.
+    ///
+    /// Newer `tokio` reactors let a task recover the handle of the reactor
+    /// it's running on, which would let this take the handle implicitly.
+    /// `tokio_core` has no such ambient handle, so a `Handle` still has to
+    /// be threaded in explicitly here.
     pub fn new(stream: S, handle: &Handle) -> io::Result<TimeoutStream<S>> {
-        let writer = TimeoutWriter::new(stream, handle)?;
-        let reader = TimeoutReader::new(writer, handle)?;
+        let handle = Rc::new(handle.clone());
+        let writer = TimeoutWriter::with_shared_handle(stream, handle.clone());
+        let reader = TimeoutReader::with_shared_handle(writer, handle);
         Ok(TimeoutStream(reader))
     }
 
@@ -336,6 +629,49 @@ where
         self.0.get_mut().set_timeout(timeout)
     }
 
+    /// Returns the current read deadline.
+    pub fn read_deadline(&self) -> Option<Duration> {
+        self.0.deadline()
+    }
+
+    /// Sets an absolute deadline for a read to complete within.
+    ///
+    /// This will reset any pending read deadline.
+    pub fn set_read_deadline(&mut self, deadline: Option<Duration>) {
+        self.0.set_deadline(deadline)
+    }
+
+    /// Returns the current write deadline.
+    pub fn write_deadline(&self) -> Option<Duration> {
+        self.0.get_ref().deadline()
+    }
+
+    /// Sets an absolute deadline for a write to complete within.
+    ///
+    /// This will reset any pending write deadline.
+    pub fn set_write_deadline(&mut self, deadline: Option<Duration>) {
+        self.0.get_mut().set_deadline(deadline)
+    }
+
+    /// Requires at least `min_bytes` to be read in each `window`. See
+    /// `TimeoutReader::set_min_rate`.
+    pub fn set_min_read_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.0.set_min_rate(min_bytes, window)
+    }
+
+    /// Requires at least `min_bytes` to be written in each `window`. See
+    /// `TimeoutWriter::set_min_rate`.
+    pub fn set_min_write_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.0.get_mut().set_min_rate(min_bytes, window)
+    }
+
+    /// Sets a handler to run when the write idle timer expires, in place of
+    /// failing the write with `TimedOut`. See
+    /// `TimeoutWriter::set_idle_handler`.
+    pub fn set_idle_handler(&mut self, handler: Option<Box<FnMut() -> io::Result<()>>>) {
+        self.0.get_mut().set_idle_handler(handler)
+    }
+
     /// Returns a shared reference to the inner stream.
     pub fn get_ref(&self) -> &S {
         self.0.get_ref().get_ref()
@@ -350,6 +686,36 @@ where
     pub fn into_inner(self) -> S {
         self.0.into_inner().into_inner()
     }
+
+    /// Splits this `TimeoutStream` into independent owned read and write
+    /// halves.
+    ///
+    /// The two halves can be moved into separate tasks. Each half carries
+    /// its own `TimeoutState`, so read and write timeouts continue to be
+    /// tracked independently, just as they are on the unsplit stream.
+    pub fn split(self) -> (ReadHalf<S>, WriteHalf<S>) {
+        let TimeoutReader {
+            reader: writer,
+            state: read_state,
+        } = self.0;
+        let TimeoutWriter {
+            writer: stream,
+            state: write_state,
+            on_idle: write_on_idle,
+        } = writer;
+        let (a, b) = BiLock::new(stream);
+        (
+            ReadHalf {
+                lock: a,
+                state: read_state,
+            },
+            WriteHalf {
+                lock: b,
+                state: write_state,
+                on_idle: write_on_idle,
+            },
+        )
+    }
 }
 
 impl<S> Read for TimeoutStream<S>
@@ -400,6 +766,262 @@ where
     }
 }
 
+/// The read half of a `TimeoutStream`, produced by `TimeoutStream::split`.
+pub struct ReadHalf<S> {
+    lock: BiLock<S>,
+    state: TimeoutState,
+}
+
+impl<S> ReadHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    /// Returns the current read timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.state.timeout()
+    }
+
+    /// Sets the read timeout.
+    ///
+    /// This will reset any pending read timeout.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.state.set_timeout(timeout);
+    }
+
+    /// Returns the current read deadline.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.state.deadline()
+    }
+
+    /// Sets an absolute deadline for a read to complete within.
+    ///
+    /// This will reset any pending read deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.state.set_deadline(deadline);
+    }
+
+    /// Requires at least `min_bytes` to be read in each `window`. See
+    /// `TimeoutReader::set_min_rate`.
+    pub fn set_min_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.state.set_min_rate(min_bytes, window);
+    }
+
+    /// Reunites this `ReadHalf` with the `WriteHalf` it was split from,
+    /// returning the original `TimeoutStream`.
+    ///
+    /// Returns an error if the two halves did not originate from the same
+    /// `TimeoutStream::split` call.
+    pub fn unsplit(self, write: WriteHalf<S>) -> io::Result<TimeoutStream<S>> {
+        match self.lock.reunite(write.lock) {
+            Ok(stream) => {
+                let writer = TimeoutWriter {
+                    writer: stream,
+                    state: write.state,
+                    on_idle: write.on_idle,
+                };
+                let reader = TimeoutReader {
+                    reader: writer,
+                    state: self.state,
+                };
+                Ok(TimeoutStream(reader))
+            }
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "tried to unsplit halves from different streams",
+            )),
+        }
+    }
+}
+
+impl<S> Read for ReadHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.read(buf);
+                match r {
+                    Ok(n) => self.state.success(n),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.state.check()?,
+                    Err(_) => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.state.check()?;
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            }
+        }
+    }
+}
+
+impl<S> AsyncRead for ReadHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        match self.lock.poll_lock() {
+            Async::Ready(inner) => inner.prepare_uninitialized_buffer(buf),
+            Async::NotReady => true,
+        }
+    }
+
+    fn read_buf<B: BufMut>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.read_buf(buf);
+                match r {
+                    Ok(Async::Ready(n)) => self.state.success(n),
+                    Ok(Async::NotReady) => self.state.check()?,
+                    Err(_) => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.state.check()?;
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+/// The write half of a `TimeoutStream`, produced by `TimeoutStream::split`.
+pub struct WriteHalf<S> {
+    lock: BiLock<S>,
+    state: TimeoutState,
+    on_idle: Option<Box<FnMut() -> io::Result<()>>>,
+}
+
+impl<S> WriteHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    /// Returns the current write timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.state.timeout()
+    }
+
+    /// Sets the write timeout.
+    ///
+    /// This will reset any pending write timeout.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.state.set_timeout(timeout);
+    }
+
+    /// Returns the current write deadline.
+    pub fn deadline(&self) -> Option<Duration> {
+        self.state.deadline()
+    }
+
+    /// Sets an absolute deadline for a write to complete within.
+    ///
+    /// This will reset any pending write deadline.
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.state.set_deadline(deadline);
+    }
+
+    /// Requires at least `min_bytes` to be written in each `window`. See
+    /// `TimeoutWriter::set_min_rate`.
+    pub fn set_min_rate(&mut self, min_bytes: usize, window: Duration) {
+        self.state.set_min_rate(min_bytes, window);
+    }
+
+    /// Sets a handler to run when the idle timer expires, in place of
+    /// failing the write with `TimedOut`. See
+    /// `TimeoutWriter::set_idle_handler`.
+    pub fn set_idle_handler(&mut self, handler: Option<Box<FnMut() -> io::Result<()>>>) {
+        self.on_idle = handler;
+    }
+
+    /// Checks the idle timer, giving the idle handler (if any) a chance to
+    /// run in place of a hard `TimedOut` failure, then checks the deadline
+    /// and minimum rate unconditionally.
+    fn check(&mut self) -> io::Result<()> {
+        self.state.check_with_idle_handler(&mut self.on_idle)
+    }
+}
+
+impl<S> Write for WriteHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.write(buf);
+                match r {
+                    Ok(n) => self.state.success(n),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.check()?,
+                    Err(_) => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.check()?;
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.flush();
+                match r {
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.check()?,
+                    _ => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.check()?;
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WriteHalf<S>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.shutdown();
+                match r {
+                    Ok(Async::NotReady) => self.check()?,
+                    _ => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.check()?;
+                Ok(Async::NotReady)
+            }
+        }
+    }
+
+    fn write_buf<B: Buf>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
+        match self.lock.poll_lock() {
+            Async::Ready(mut inner) => {
+                let r = inner.write_buf(buf);
+                match r {
+                    Ok(Async::Ready(n)) => self.state.success(n),
+                    Ok(Async::NotReady) => self.check()?,
+                    Err(_) => self.state.reset_idle(),
+                }
+                r
+            }
+            Async::NotReady => {
+                self.check()?;
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use futures::Async;
@@ -497,6 +1119,114 @@ mod test {
         core.run(ReadFuture(Some(reader))).unwrap();
     }
 
+    #[test]
+    fn read_deadline() {
+        let mut core = Core::new().unwrap();
+
+        // never completes within the test, so every read hits `WouldBlock`
+        // and gives the deadline timer a chance to be polled
+        let reader = DelayStream(Timeout::new(Duration::from_secs(1), &core.handle()).unwrap());
+        let mut reader = TimeoutReader::new(reader, &core.handle()).unwrap();
+        reader.set_timeout(Some(Duration::from_secs(1)));
+        reader.set_deadline(Some(Duration::from_millis(100)));
+
+        let r = core.run(ReadFuture(Some(reader)));
+        assert_eq!(r.err().unwrap().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn read_min_rate() {
+        let mut core = Core::new().unwrap();
+
+        // never completes a read during the test, so no bytes are ever
+        // credited against the rate window
+        let reader = DelayStream(Timeout::new(Duration::from_secs(1), &core.handle()).unwrap());
+        let mut reader = TimeoutReader::new(reader, &core.handle()).unwrap();
+        reader.set_timeout(Some(Duration::from_secs(1)));
+        reader.set_min_rate(1, Duration::from_millis(100));
+
+        let r = core.run(ReadFuture(Some(reader)));
+        assert_eq!(r.err().unwrap().kind(), io::ErrorKind::TimedOut);
+    }
+
+    struct OnceStream {
+        timeout: Timeout,
+        done: bool,
+    }
+
+    impl OnceStream {
+        fn new(delay: Duration, handle: &Handle) -> OnceStream {
+            OnceStream {
+                timeout: Timeout::new(delay, handle).unwrap(),
+                done: false,
+            }
+        }
+    }
+
+    impl Read for OnceStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.done {
+                return Err(io::Error::from(io::ErrorKind::WouldBlock));
+            }
+
+            if self.timeout.poll()?.is_ready() {
+                self.done = true;
+                buf[0] = 0;
+                Ok(1)
+            } else {
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            }
+        }
+    }
+
+    impl AsyncRead for OnceStream {
+        unsafe fn prepare_uninitialized_buffer(&self, _: &mut [u8]) -> bool {
+            true
+        }
+    }
+
+    struct DrainFuture<S>(Option<S>);
+
+    impl<S> Future for DrainFuture<S>
+    where
+        S: AsyncRead,
+    {
+        type Item = ();
+        type Error = io::Error;
+
+        fn poll(&mut self) -> Poll<(), io::Error> {
+            let mut reader = self.0.take().unwrap();
+
+            loop {
+                let mut buf = [0; 1];
+                match reader.read(&mut buf) {
+                    Ok(_) => continue,
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.0 = Some(reader);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn read_min_rate_detects_silence_after_healthy_window() {
+        let mut core = Core::new().unwrap();
+
+        // completes one read quickly, satisfying the first window, then
+        // goes silent forever: exercises the window roll-over path, as
+        // opposed to `read_min_rate` above which never satisfies even the
+        // first window
+        let reader = OnceStream::new(Duration::from_millis(20), &core.handle());
+        let mut reader = TimeoutReader::new(reader, &core.handle()).unwrap();
+        reader.set_min_rate(1, Duration::from_millis(100));
+
+        let r = core.run(DrainFuture(Some(reader)));
+        assert_eq!(r.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
     struct WriteFuture(TimeoutWriter<DelayStream>);
 
     impl Future for WriteFuture {
@@ -535,6 +1265,44 @@ mod test {
         core.run(WriteFuture(writer)).unwrap();
     }
 
+    #[test]
+    fn write_idle_handler() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut core = Core::new().unwrap();
+
+        let writer = DelayStream(Timeout::new(Duration::from_millis(300), &core.handle()).unwrap());
+        let mut writer = TimeoutWriter::new(writer, &core.handle()).unwrap();
+        writer.set_timeout(Some(Duration::from_millis(100)));
+
+        let calls = Rc::new(RefCell::new(0));
+        let handler_calls = calls.clone();
+        writer.set_idle_handler(Some(Box::new(move || {
+            *handler_calls.borrow_mut() += 1;
+            Ok(())
+        })));
+
+        core.run(WriteFuture(writer)).unwrap();
+        assert!(*calls.borrow() > 0);
+    }
+
+    #[test]
+    fn write_idle_handler_does_not_swallow_deadline() {
+        let mut core = Core::new().unwrap();
+
+        // never completes within the test, so every write hits `WouldBlock`
+        // and keeps retriggering the idle timer
+        let writer = DelayStream(Timeout::new(Duration::from_secs(1), &core.handle()).unwrap());
+        let mut writer = TimeoutWriter::new(writer, &core.handle()).unwrap();
+        writer.set_timeout(Some(Duration::from_millis(20)));
+        writer.set_deadline(Some(Duration::from_millis(100)));
+        writer.set_idle_handler(Some(Box::new(|| Ok(()))));
+
+        let r = core.run(WriteFuture(writer));
+        assert_eq!(r.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
     #[test]
     fn tcp_read() {
         let mut core = Core::new().unwrap();
@@ -565,4 +1333,30 @@ mod test {
             Err(e) => panic!("{:?}", e),
         }
     }
+
+    #[test]
+    fn split_and_unsplit() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut socket = listener.accept().unwrap().0;
+            thread::sleep(Duration::from_millis(10));
+            socket.write_all(b"f").unwrap();
+        });
+
+        let f = TcpStream::connect(&addr, &handle).and_then(move |s| {
+            let s = TimeoutStream::new(s, &handle).unwrap();
+            let (mut read, write) = s.split();
+            read.set_timeout(Some(Duration::from_millis(500)));
+            ReadFuture(Some(read)).map(move |read| (read, write))
+        });
+        let (read, write) = core.run(f).unwrap();
+
+        assert_eq!(read.timeout(), Some(Duration::from_millis(500)));
+        read.unsplit(write).unwrap();
+    }
 }